@@ -3,33 +3,45 @@
 // found in the LICENSE file.
 
 //! A TEE application life-cycle manager.
+//!
+//! Session recording (`AppManifestEntry::record_session`) and the management RPC surface
+//! (`Trichechus::{list_apps,get_app_info,stop_app}`, `AppStatus`, `TrichechusClient`) depend on
+//! companion changes to the `libsirenia`/`sirenia` crates that this tree vendors by path but does
+//! not itself contain; they're assumed to land alongside this series rather than being introduced
+//! here.
 
 use std::io::stderr;
 use std::os::unix::io::AsRawFd;
 
-use std::cell::RefCell;
-use std::collections::{HashMap, VecDeque};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::env;
 use std::fmt::Debug;
+use std::fs;
+use std::io::Read;
 use std::mem::swap;
-use std::ops::{Deref, DerefMut};
-use std::os::unix::io::RawFd;
+use std::net::IpAddr;
+use std::os::unix::io::{FromRawFd, RawFd};
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::result::Result as StdResult;
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use getopts::Options;
+use libc::pid_t;
 use libchromeos::secure_blob::SecureBlob;
 use libsirenia::{
     build_info::BUILD_TIMESTAMP,
     cli::{trichechus::initialize_common_arguments, TransportTypeOption},
     communication::{
         persistence::{Cronista, CronistaClient, Status},
-        trichechus::{AppInfo, Trichechus, TrichechusServer},
+        trichechus::{AppInfo, AppStatus, Trichechus, TrichechusClient, TrichechusServer},
         StorageRpc, StorageRpcServer,
     },
     linux::{
-        events::{AddEventSourceMutator, EventMultiplexer, Mutator},
+        events::{AddEventSourceMutator, EventMultiplexer, EventSource, Mutator},
         syslog::{Syslog, SyslogReceiverMut, SYSLOG_PATH},
     },
     rpc::{self, ConnectionHandler, RpcDispatcher, TransportServer},
@@ -50,13 +62,48 @@ use sirenia::{
     },
 };
 use sys_util::{
-    self, error, getpid, getsid, info, setsid, syslog, vsock::SocketAddr as VSocketAddr,
+    self, error, getpid, getsid, info, setsid, syslog, vsock::SocketAddr as VSocketAddr, EventFd,
+    SignalFd, TimerFd,
 };
 use thiserror::Error as ThisError;
 
 const CRONISTA_URI_SHORT_NAME: &str = "C";
 const CRONISTA_URI_LONG_NAME: &str = "cronista";
 const SYSLOG_PATH_SHORT_NAME: &str = "L";
+const APP_MANIFEST_SHORT_NAME: &str = "m";
+const APP_MANIFEST_LONG_NAME: &str = "app-manifest";
+const SHUTDOWN_GRACE_PERIOD_SHORT_NAME: &str = "g";
+const SHUTDOWN_GRACE_PERIOD_LONG_NAME: &str = "shutdown-grace-period-secs";
+const DEFAULT_SHUTDOWN_GRACE_PERIOD_SECS: u64 = 5;
+/// How often the sweep in `DeadAppSweeper` checks `running_apps` for entries whose TEE app has
+/// exited but whose SIGCHLD was missed (e.g. blocked or lost).
+const DEAD_APP_SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+const CONTROL_CONN_RATE_LONG_NAME: &str = "control-conn-rate";
+const CONTROL_CONN_BURST_LONG_NAME: &str = "control-conn-burst";
+const APP_CONN_RATE_LONG_NAME: &str = "app-conn-rate";
+const APP_CONN_BURST_LONG_NAME: &str = "app-conn-burst";
+const DEFAULT_CONTROL_CONN_RATE: f64 = 1.0;
+const DEFAULT_CONTROL_CONN_BURST: f64 = 5.0;
+const DEFAULT_APP_CONN_RATE: f64 = 5.0;
+const DEFAULT_APP_CONN_BURST: f64 = 20.0;
+/// How long a source's token bucket may sit unused before it is evicted, bounding the memory a
+/// flood of one-off sources can make the rate limiter hold onto.
+const RATE_LIMITER_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+const SINGLE_THREADED_LONG_NAME: &str = "single-threaded";
+const WORKER_THREADS_LONG_NAME: &str = "worker-threads";
+/// Number of worker threads the connection dispatcher spawns by default when not running
+/// `--single-threaded`.
+const DEFAULT_WORKER_THREADS: usize = 4;
+
+const SESSION_RECORDING_RING_BYTES_LONG_NAME: &str = "session-recording-ring-bytes";
+/// Bounds how much of a TEE app's captured stdio a `SessionRecording` keeps (and ultimately
+/// persists) per app, so a chatty app with `record_session` set can't exhaust storage.
+const DEFAULT_SESSION_RECORDING_RING_BYTES: usize = 1024 * 1024;
+
+const STATUS_LONG_NAME: &str = "status";
+const STOP_LONG_NAME: &str = "stop";
 
 #[derive(ThisError, Debug)]
 pub enum Error {
@@ -78,21 +125,247 @@ pub enum Error {
     AppManifest(app_info::Error),
     #[error("Sandbox type not implemented for: {0:?}")]
     SandboxTypeNotImplemented(AppManifestEntry),
+    #[error("failed to read app manifest file '{0}': {1}")]
+    ReadAppManifest(PathBuf, std::io::Error),
+    #[error("failed to parse app manifest file '{0}': {1}")]
+    ParseAppManifest(PathBuf, String),
+    #[error("app manifest entry '{0}' requests an encryption key version the secret manager cannot derive")]
+    UnsupportedEncryptionKeyVersion(String),
+    #[error("failed to create signal handler: {0}")]
+    CreateSignalFd(sys_util::Error),
+    #[error("failed to read pending signal: {0}")]
+    ReadSignalFd(sys_util::Error),
+    #[error("failed to create the dead app sweep timer: {0}")]
+    CreateTimerFd(sys_util::Error),
+    #[error("failed to arm the dead app sweep timer: {0}")]
+    ArmTimerFd(sys_util::Error),
+    #[error("failed to create worker wake eventfd: {0}")]
+    CreateEventFd(sys_util::Error),
+    #[error("management RPC failed: {0}")]
+    ManagementRpc(rpc::Error),
 }
 
 /// The result of an operation in this crate.
 pub type Result<T> = StdResult<T, Error>;
 
+/// Which stdio stream a `RecordingFrame` came from, matching asciicast v2's `o`/`e` stream tags
+/// (trichechus never captures stdin, so there is no `i`).
+#[derive(Clone, Copy, Debug)]
+enum RecordingStream {
+    Stdout,
+    Stderr,
+}
+
+impl RecordingStream {
+    fn tag(self) -> &'static str {
+        match self {
+            RecordingStream::Stdout => "o",
+            RecordingStream::Stderr => "e",
+        }
+    }
+}
+
+/// One chunk of a TEE app's captured stdio, timestamped relative to when recording started so
+/// the cast can be replayed frame-by-frame at its original pacing.
+struct RecordingFrame {
+    offset: Duration,
+    stream: RecordingStream,
+    data: Vec<u8>,
+}
+
+/// An append-only, size-bounded recording of a TEE app's stdio. Bounded by `max_bytes`: once
+/// exceeded, the oldest frames are dropped so a chatty app can't grow a recording without bound
+/// (the same silent-eviction approach `RateLimiter` uses for idle buckets).
+struct SessionRecording {
+    start: Instant,
+    frames: VecDeque<RecordingFrame>,
+    used_bytes: usize,
+    max_bytes: usize,
+}
+
+impl SessionRecording {
+    fn new(max_bytes: usize) -> Self {
+        SessionRecording {
+            start: Instant::now(),
+            frames: VecDeque::new(),
+            used_bytes: 0,
+            max_bytes,
+        }
+    }
+
+    fn push(&mut self, stream: RecordingStream, data: Vec<u8>) {
+        self.used_bytes += data.len();
+        self.frames.push_back(RecordingFrame {
+            offset: self.start.elapsed(),
+            stream,
+            data,
+        });
+        while self.used_bytes > self.max_bytes {
+            match self.frames.pop_front() {
+                Some(dropped) => self.used_bytes -= dropped.data.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Renders the recording as an asciicast-v2-style cast: a JSON header line followed by one
+    /// `[offset_secs, stream_tag, data]` line per frame. Hand-rolled since this crate has no
+    /// `serde_json` dependency.
+    fn to_cast(&self, title: &str) -> Vec<u8> {
+        let mut out = format!(
+            "{{\"version\": 2, \"width\": 80, \"height\": 24, \"title\": \"{}\"}}\n",
+            escape_json_string(title)
+        );
+        for frame in &self.frames {
+            out.push_str(&format!(
+                "[{:.6}, \"{}\", \"{}\"]\n",
+                frame.offset.as_secs_f64(),
+                frame.stream.tag(),
+                escape_json_string(&String::from_utf8_lossy(&frame.data))
+            ));
+        }
+        out.into_bytes()
+    }
+}
+
+/// Escapes a string for embedding in the hand-rolled JSON cast format `SessionRecording::to_cast`
+/// produces.
+fn escape_json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod session_recording_tests {
+    use super::*;
+
+    #[test]
+    fn escape_json_string_escapes_special_characters() {
+        assert_eq!(
+            escape_json_string("say \"hi\"\\there\n\tand\r\x01"),
+            "say \\\"hi\\\"\\\\there\\n\\tand\\r\\u0001"
+        );
+        assert_eq!(escape_json_string("plain text"), "plain text");
+    }
+
+    #[test]
+    fn session_recording_push_evicts_oldest_frames_past_max_bytes() {
+        let mut recording = SessionRecording::new(5);
+        recording.push(RecordingStream::Stdout, b"abc".to_vec());
+        recording.push(RecordingStream::Stdout, b"de".to_vec());
+        assert_eq!(recording.used_bytes, 5);
+        assert_eq!(recording.frames.len(), 2);
+
+        // Pushing one more byte exceeds max_bytes, so the oldest frame ("abc") should be dropped
+        // rather than growing the recording without bound.
+        recording.push(RecordingStream::Stdout, b"f".to_vec());
+        assert_eq!(recording.used_bytes, 3);
+        assert_eq!(recording.frames.len(), 2);
+        assert_eq!(recording.frames[0].data, b"de".to_vec());
+        assert_eq!(recording.frames[1].data, b"f".to_vec());
+    }
+}
+
+/// Reads one stdio stream of a sandboxed TEE app from its end of a pipe and appends each chunk
+/// it receives to the shared `SessionRecording` as a frame, in place of handing the stream
+/// straight to the terminal.
+struct RecordingReader {
+    stream: RecordingStream,
+    pipe: fs::File,
+    recording: Arc<Mutex<SessionRecording>>,
+    /// The owning `TeeApp`'s set of fds that are still registered with an `EventMultiplexer`.
+    /// Shared with it so that when this reader removes itself on EOF below, later eviction of
+    /// the app (e.g. by `ChildReaper`) doesn't also ask to remove this same fd number, which the
+    /// multiplexer may since have reassigned to an unrelated, newly registered `EventSource`.
+    live_fds: Arc<Mutex<HashSet<RawFd>>>,
+}
+
+impl AsRawFd for RecordingReader {
+    fn as_raw_fd(&self) -> RawFd {
+        self.pipe.as_raw_fd()
+    }
+}
+
+impl EventSource for RecordingReader {
+    fn on_event(&mut self) -> StdResult<Option<Box<dyn Mutator>>, String> {
+        let mut buf = [0u8; 4096];
+        let len = self.pipe.read(&mut buf).map_err(|e| e.to_string())?;
+        if len == 0 {
+            // EOF: the write end closed, which happens once the TEE app exits. A pipe at EOF
+            // stays readable forever under the level-triggered multiplexer, so without removing
+            // ourselves here this would spin `on_event` in a tight loop.
+            let fd = self.as_raw_fd();
+            self.live_fds.lock().unwrap().remove(&fd);
+            return Ok(Some(Box::new(RemoveFdsMutator(vec![fd]))));
+        }
+        self.recording
+            .lock()
+            .unwrap()
+            .push(self.stream, buf[..len].to_vec());
+        Ok(None)
+    }
+}
+
+/// Opens a pipe for capturing one stdio stream of a TEE app being recorded, returning the read
+/// end trichechus keeps (wrapped in a `RecordingReader`) and the write end's fd, which the caller
+/// hands to the sandboxed app via `keep_fds`.
+fn open_recording_pipe() -> Result<(fs::File, RawFd)> {
+    let mut fds: [libc::c_int; 2] = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } < 0 {
+        return Err(Error::OpenPipe(sys_util::Error::last()));
+    }
+    let read_end = unsafe { fs::File::from_raw_fd(fds[0]) };
+    Ok((read_end, fds[1]))
+}
+
 /* Holds the trichechus-relevant information for a TEEApp. */
 struct TeeApp {
     _sandbox: Sandbox,
     app_info: AppManifestEntry,
+    pid: pid_t,
+    dispatcher_fd: RawFd,
+    /// Set when `app_info.record_session` requested a session recording.
+    recording: Option<Arc<Mutex<SessionRecording>>>,
+    /// Read ends of this app's recording pipes, so they can be evicted from the
+    /// `EventMultiplexer` alongside `dispatcher_fd` when the app exits.
+    recording_fds: Vec<RawFd>,
+    /// This app's fds that are still registered with an `EventMultiplexer`: initially
+    /// `dispatcher_fd` plus `recording_fds`, shrinking as each `RecordingReader` removes its own
+    /// fd on EOF. Shared with every `RecordingReader` spawned for this app so eviction only ever
+    /// asks to remove fds that are actually still registered, rather than a stale fd number the
+    /// multiplexer may have since reassigned to an unrelated `EventSource`.
+    live_fds: Arc<Mutex<HashSet<RawFd>>>,
+    /// When this app was spawned, used to compute its uptime for the `list_apps` management RPC.
+    started: Instant,
+    /// Which worker's `EventMultiplexer` this app's `EventSource`s (`dispatcher_fd` and
+    /// `recording_fds`) are registered on, so eviction can be routed there. `None` means they're
+    /// on the main ctx, i.e. this app was spawned under `Dispatch::Direct`.
+    owner: Option<usize>,
+}
+
+impl TeeApp {
+    /// This app's `EventSource` fds that are still registered with an `EventMultiplexer`. Used
+    /// to evict all of them once the app exits.
+    fn event_source_fds(&self) -> Vec<RawFd> {
+        self.live_fds.lock().unwrap().iter().copied().collect()
+    }
 }
 
 #[derive(Clone)]
 struct TeeAppHandler {
-    state: Rc<RefCell<TrichechusState>>,
-    tee_app: Rc<RefCell<TeeApp>>,
+    state: Arc<TrichechusState>,
+    tee_app: Arc<RwLock<TeeApp>>,
 }
 
 impl TeeAppHandler {
@@ -107,39 +380,41 @@ impl TeeAppHandler {
         &self,
         cb: F,
     ) -> StdResult<T, ()> {
-        let app_info = &self.tee_app.borrow().app_info;
+        let tee_app = self.tee_app.read().unwrap();
+        let app_info = &tee_app.app_info;
         let params = app_info.storage_parameters.as_ref().ok_or_else(|| {
             error!(
                 "App id '{}' made an unconfigured call to the write_data storage API.",
                 &app_info.app_name
             );
         })?;
-        let state = self.state.borrow_mut();
-        // Holds the RefMut until secret_manager is dropped.
-        let wrapper = &mut state.secret_manager.borrow_mut();
-        let secret_manager = wrapper.deref_mut();
 
         // If the operation fails with an rpc::Error, try again.
         for x in 0..=1 {
             // If already connected try once, to see if the connection dropped.
-            if let Some(persistence) = (*state.persistence.borrow().deref()).as_ref() {
+            let persistence_guard = self.state.persistence.read().unwrap();
+            if let Some(persistence) = persistence_guard.as_ref() {
+                // Holds the write lock until secret_manager is dropped.
+                let mut secret_manager = self.state.secret_manager.write().unwrap();
                 let encryption: StorageEncryption;
                 let ret = cb(
-                    &params,
+                    params,
                     match params.encryption_key_version {
                         Some(_) => {
                             // TODO Move this to TrichechusState.
                             encryption =
-                                StorageEncryption::new(app_info, secret_manager, persistence);
+                                StorageEncryption::new(app_info, &mut secret_manager, persistence);
                             &encryption as &dyn Cronista<Error = rpc::Error>
                         }
                         None => persistence as &dyn Cronista<Error = rpc::Error>,
                     },
                 );
+                drop(secret_manager);
                 match ret {
                     Err(err) => {
                         // If the client is no longer valid, drop it so it will be recreated on the next call.
-                        state.drop_persistence();
+                        drop(persistence_guard);
+                        self.state.drop_persistence();
                         error!("failed to persist data: {}", err);
                         if x == 1 {
                             break;
@@ -147,9 +422,11 @@ impl TeeAppHandler {
                     }
                     Ok(a) => return Ok(a),
                 }
+            } else {
+                drop(persistence_guard);
             }
 
-            state.check_persistence().map_err(|err| {
+            self.state.check_persistence().map_err(|err| {
                 error!("failed to persist data: {}", err);
             })?;
         }
@@ -178,45 +455,86 @@ impl StorageRpc for TeeAppHandler {
     }
 }
 
+/// Shared trichechus state. Every mutable field is locked individually (rather than behind one
+/// outer lock) so e.g. a slow `CronistaClient` reconnect under `persistence`'s write lock doesn't
+/// stall workers that only need `running_apps`. `TrichechusState` itself is shared via a plain
+/// `Arc`, not an `Arc<RwLock<_>>`.
 struct TrichechusState {
-    expected_port: u32,
-    pending_apps: HashMap<TransportType, String>,
-    running_apps: HashMap<TransportType, Rc<RefCell<TeeApp>>>,
-    log_queue: VecDeque<Vec<u8>>,
-    persistence_uri: TransportType,
-    persistence: RefCell<Option<CronistaClient>>,
-    secret_manager: RefCell<SecretManager>,
+    expected_port: RwLock<u32>,
+    pending_apps: RwLock<HashMap<TransportType, String>>,
+    running_apps: RwLock<HashMap<TransportType, Arc<RwLock<TeeApp>>>>,
+    log_queue: RwLock<VecDeque<Vec<u8>>>,
+    persistence_uri: RwLock<TransportType>,
+    persistence: RwLock<Option<CronistaClient>>,
+    secret_manager: RwLock<SecretManager>,
     app_manifest: AppManifest,
+    shutdown_requested: RwLock<bool>,
+    /// Byte cap applied to every TEE app's `SessionRecording`. Read-only after construction, so
+    /// it doesn't need its own lock.
+    max_recording_bytes: usize,
+    /// How incoming connections (and fd eviction for running apps) are routed to worker ctxs.
+    /// `None` until `set_dispatch` is called once workers have been spawned during startup;
+    /// behaves like `Dispatch::Direct` until then.
+    dispatch: RwLock<Option<Dispatch>>,
 }
 
 impl TrichechusState {
-    fn new(platform_secret: PlatformSecret, gsc_secret: GscSecret) -> Self {
-        let app_manifest = AppManifest::new();
+    fn new(
+        platform_secret: PlatformSecret,
+        gsc_secret: GscSecret,
+        app_manifest: AppManifest,
+        max_recording_bytes: usize,
+    ) -> Self {
         // There isn't any way to recover if the secret derivation process fails.
         let secret_manager =
             SecretManager::new(platform_secret, gsc_secret, &app_manifest).unwrap();
 
         TrichechusState {
-            expected_port: DEFAULT_CLIENT_PORT,
-            pending_apps: HashMap::new(),
-            running_apps: HashMap::new(),
-            log_queue: VecDeque::new(),
-            persistence_uri: TransportType::VsockConnection(VSocketAddr {
+            expected_port: RwLock::new(DEFAULT_CLIENT_PORT),
+            pending_apps: RwLock::new(HashMap::new()),
+            running_apps: RwLock::new(HashMap::new()),
+            log_queue: RwLock::new(VecDeque::new()),
+            persistence_uri: RwLock::new(TransportType::VsockConnection(VSocketAddr {
                 cid: CROS_CID,
                 port: DEFAULT_CRONISTA_PORT,
-            }),
-            persistence: RefCell::new(None),
+            })),
+            persistence: RwLock::new(None),
             app_manifest,
-            secret_manager: RefCell::new(secret_manager),
+            secret_manager: RwLock::new(secret_manager),
+            shutdown_requested: RwLock::new(false),
+            max_recording_bytes,
+            dispatch: RwLock::new(None),
         }
     }
 
+    /// Installs the `Dispatch` built once workers have been spawned during startup, so
+    /// eviction (and, via `DugongConnectionHandler`, new connections) can be routed to them.
+    fn set_dispatch(&self, dispatch: Dispatch) {
+        *self.dispatch.write().unwrap() = Some(dispatch);
+    }
+
+    /// Tells every worker thread to drain its ctx and exit. Unlike the main ctx, which notices
+    /// `is_shutdown_requested()` on its own each time around its event loop, a worker only ever
+    /// wakes on its `WorkQueue`'s `wake_fd`, so it has to be told explicitly.
+    fn shutdown_workers(&self) {
+        if let Some(dispatch) = self.dispatch.read().unwrap().as_ref() {
+            dispatch.shutdown_workers();
+        }
+    }
+
+    /// Double-checked locking: most calls only need the read lock to discover a client already
+    /// exists. Only the (rare) first caller after startup or a drop takes the write lock, and
+    /// re-checks under it so two racing callers can't each open a redundant connection.
     fn check_persistence(&self) -> Result<()> {
-        if self.persistence.borrow().is_some() {
+        if self.persistence.read().unwrap().is_some() {
+            return Ok(());
+        }
+        let mut persistence = self.persistence.write().unwrap();
+        if persistence.is_some() {
             return Ok(());
         }
-        let uri = self.persistence_uri.clone();
-        *self.persistence.borrow_mut().deref_mut() = Some(CronistaClient::new(
+        let uri = self.persistence_uri.read().unwrap().clone();
+        *persistence = Some(CronistaClient::new(
             uri.try_into_client(None)
                 .unwrap()
                 .connect()
@@ -226,24 +544,255 @@ impl TrichechusState {
     }
 
     fn drop_persistence(&self) {
-        *self.persistence.borrow_mut().deref_mut() = None;
+        *self.persistence.write().unwrap() = None;
+    }
+
+    /// Persists any syslog data that was buffered but not yet fetched, rather than silently
+    /// dropping it when trichechus exits.
+    fn flush_log_queue(&self) {
+        let mut log_queue = self.log_queue.write().unwrap();
+        if log_queue.is_empty() {
+            return;
+        }
+        if let Err(err) = self.check_persistence() {
+            error!("failed to flush log queue on shutdown: {}", err);
+            return;
+        }
+        if let Some(persistence) = self.persistence.read().unwrap().as_ref() {
+            for entry in log_queue.drain(..) {
+                if let Err(err) = persistence.persist(
+                    "syslog".to_string(),
+                    "trichechus".to_string(),
+                    "shutdown".to_string(),
+                    entry,
+                ) {
+                    error!("failed to persist queued log entry on shutdown: {}", err);
+                }
+            }
+        }
+    }
+
+    fn request_shutdown(&self) {
+        *self.shutdown_requested.write().unwrap() = true;
+    }
+
+    fn is_shutdown_requested(&self) -> bool {
+        *self.shutdown_requested.read().unwrap()
+    }
+
+    /// Performs one non-blocking `waitpid(-1, WNOHANG)` and, if it reaped an exited child,
+    /// evicts the matching `TeeApp` from `running_apps` and persists its session recording (if
+    /// it had one). The `waitpid` and the lookup-by-pid both happen under the same
+    /// `running_apps` write lock acquisition, so a `spawn_tee_app` racing on another worker
+    /// can't insert a new entry under the just-reaped pid in the window between the two — it
+    /// would otherwise be possible for the kernel to recycle the pid to a brand new app before
+    /// we got around to matching the old one by it, leaking the real dead entry and evicting the
+    /// new app's fds instead.
+    ///
+    /// Returns `None` once there is no more exited child left to reap; otherwise returns the
+    /// reaped pid and the `EventSource` fds (if any) of the app evicted for it. The fds are
+    /// empty when the reaped pid doesn't match any entry in `running_apps`, e.g. it belongs to
+    /// a grandchild process that isn't itself a tracked TEE app.
+    fn reap_and_evict_one(&self) -> Option<(pid_t, Vec<RawFd>)> {
+        let tee_app = {
+            let mut running_apps = self.running_apps.write().unwrap();
+            let mut status: libc::c_int = 0;
+            // WNOHANG: don't block if there's no exited child left to reap.
+            let pid = unsafe { libc::waitpid(-1, &mut status, libc::WNOHANG) };
+            if pid <= 0 {
+                return None;
+            }
+            info!("tee app with pid {} exited with status {}", pid, status);
+            let id = running_apps
+                .iter()
+                .find(|(_, tee_app)| tee_app.read().unwrap().pid == pid)
+                .map(|(id, _)| id.clone());
+            (pid, id.and_then(|id| running_apps.remove(&id)))
+        };
+        let (pid, tee_app) = tee_app;
+        let fds = match tee_app {
+            // Scoped so the `running_apps` write lock above is released before
+            // `persist_recording`, which may perform a blocking `CronistaClient` RPC (and
+            // reconnect under `persistence`'s write lock) — holding `running_apps` through that
+            // would stall every other worker needing it on a network round-trip.
+            Some(tee_app) => {
+                let tee_app = tee_app.read().unwrap();
+                self.persist_recording(&tee_app);
+                self.evict_fds(tee_app.owner, tee_app.event_source_fds())
+            }
+            None => Vec::new(),
+        };
+        Some((pid, fds))
+    }
+
+    /// Routes a set of `EventSource` fds to remove to whichever ctx actually owns them. The
+    /// reaper and sweeper both run on the main ctx, but in `Dispatch::Workers` mode a TEE app's
+    /// `RpcDispatcher`/`RecordingReader`s live on whichever worker ctx spawned them — removing
+    /// them from the main ctx would silently no-op (the fd was never registered there) and leak
+    /// them on the worker forever, re-growing the `EventMultiplexer` without bound. `owner` is
+    /// `None` for apps spawned directly on the main ctx (`Dispatch::Direct`), in which case the
+    /// caller removes `fds` itself; otherwise the removal is dispatched to the owning worker and
+    /// an empty vec is returned.
+    fn evict_fds(&self, owner: Option<usize>, fds: Vec<RawFd>) -> Vec<RawFd> {
+        let idx = match owner {
+            None => return fds,
+            Some(idx) => idx,
+        };
+        if let Some(dispatch) = self.dispatch.read().unwrap().as_ref() {
+            dispatch.remove_fds(idx, fds);
+        }
+        Vec::new()
+    }
+
+    /// Sweeps `running_apps` for entries whose process has exited without trichechus having
+    /// reaped it, as a backstop for a SIGCHLD that `ChildReaper` never saw (e.g. it was blocked
+    /// or lost, rather than merely coalesced with another child's, which `ChildReaper`'s own
+    /// `waitpid(-1, ..., WNOHANG)` drain loop already handles). Persists each evicted app's
+    /// session recording (if any) and returns the `EventSource` fds it evicted.
+    ///
+    /// Like `reap_and_evict_one`, the `process_has_exited` check (which itself calls `waitpid`)
+    /// and the removal from `running_apps` both happen under one continuous write lock
+    /// acquisition below, so this isn't susceptible to the same pid-reuse race a reap-then-
+    /// separately-look-up pattern would have.
+    fn sweep_dead_apps(&self) -> Vec<RawFd> {
+        // Scoped so the `running_apps` write lock is released before `persist_recording` below
+        // runs for each evicted app, for the same reason as `reap_and_evict_one`: persisting may
+        // block on a network round-trip and shouldn't stall other workers needing this lock.
+        let dead_apps: Vec<Arc<RwLock<TeeApp>>> = {
+            let mut running_apps = self.running_apps.write().unwrap();
+            let dead_ids: Vec<TransportType> = running_apps
+                .iter()
+                .filter(|(_, tee_app)| process_has_exited(tee_app.read().unwrap().pid))
+                .map(|(id, _)| id.clone())
+                .collect();
+            dead_ids
+                .into_iter()
+                .filter_map(|id| running_apps.remove(&id))
+                .collect()
+        };
+        dead_apps
+            .into_iter()
+            .flat_map(|tee_app| {
+                let tee_app = tee_app.read().unwrap();
+                info!(
+                    "sweeping tee app '{}' (pid {}) that exited without being reaped",
+                    tee_app.app_info.app_name, tee_app.pid
+                );
+                self.persist_recording(&tee_app);
+                self.evict_fds(tee_app.owner, tee_app.event_source_fds())
+            })
+            .collect()
+    }
+
+    /// Returns the status of every currently running TEE app, for the `list_apps` management
+    /// RPC.
+    fn list_apps(&self) -> Vec<AppStatus> {
+        self.running_apps
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(id, tee_app)| {
+                let tee_app = tee_app.read().unwrap();
+                AppStatus {
+                    app_id: tee_app.app_info.app_name.clone(),
+                    transport_id: format!("{:?}", id),
+                    sandbox_type: tee_app.app_info.sandbox_type.clone(),
+                    uptime_secs: tee_app.started.elapsed().as_secs(),
+                }
+            })
+            .collect()
+    }
+
+    /// Sends SIGTERM to the running TEE app whose transport id (as rendered by `{:?}`) matches
+    /// `transport_id`, for the `stop_app` management RPC. Teardown and eviction from
+    /// `running_apps` happens the normal way once the app exits: through `ChildReaper`, or
+    /// `DeadAppSweeper` as a backstop.
+    fn stop_app(&self, transport_id: &str) -> Option<pid_t> {
+        let running_apps = self.running_apps.read().unwrap();
+        let pid = running_apps
+            .iter()
+            .find(|(id, _)| format!("{:?}", id) == transport_id)
+            .map(|(_, tee_app)| tee_app.read().unwrap().pid)?;
+        unsafe { libc::kill(pid, libc::SIGTERM) };
+        Some(pid)
+    }
+
+    /// Persists a finished TEE app's session recording through the existing `Cronista` path,
+    /// under the app's own storage scope, so it can later be fetched and replayed frame-by-frame
+    /// for incident forensics. A no-op if the app wasn't being recorded, or has no storage scope
+    /// configured to persist the recording under.
+    fn persist_recording(&self, tee_app: &TeeApp) {
+        let recording = match &tee_app.recording {
+            Some(recording) => recording,
+            None => return,
+        };
+        let params = match &tee_app.app_info.storage_parameters {
+            Some(params) => params,
+            None => {
+                error!(
+                    "tee app '{}' has a session recording but no storage scope to persist it under",
+                    tee_app.app_info.app_name
+                );
+                return;
+            }
+        };
+        let cast = recording
+            .lock()
+            .unwrap()
+            .to_cast(&tee_app.app_info.app_name);
+        if let Err(err) = self.check_persistence() {
+            error!("failed to persist session recording: {}", err);
+            return;
+        }
+        if let Some(persistence) = self.persistence.read().unwrap().as_ref() {
+            if let Err(err) = persistence.persist(
+                params.scope.clone(),
+                params.domain.to_string(),
+                "session_recording".to_string(),
+                cast,
+            ) {
+                error!(
+                    "failed to persist session recording for '{}': {}",
+                    tee_app.app_info.app_name, err
+                );
+            }
+        }
     }
 }
 
-impl SyslogReceiverMut for TrichechusState {
+/// Non-blocking check for whether `pid` has exited, reaping it if so. Unlike `kill(pid, 0)`,
+/// which reports a zombie (an exited-but-unreaped child) as alive, this correctly detects exit
+/// via `waitpid`. ECHILD (no such child, e.g. it was already reaped by `ChildReaper`'s
+/// `waitpid(-1, ...)` drain loop between `running_apps` being read and this call) is also
+/// treated as exited, so a stale entry still gets swept.
+fn process_has_exited(pid: pid_t) -> bool {
+    let mut status: libc::c_int = 0;
+    match unsafe { libc::waitpid(pid, &mut status, libc::WNOHANG) } {
+        0 => false,
+        n if n == pid => true,
+        _ => true,
+    }
+}
+
+/// Adapts the `Rc<RefCell<_>>`-based receiver `Syslog` expects to `TrichechusState`'s shared
+/// `Arc`: the `&mut self` `SyslogReceiverMut` requires only needs to be unique over this thin
+/// wrapper, since the data it touches lives behind its own lock either way.
+struct SyslogSink(Arc<TrichechusState>);
+
+impl SyslogReceiverMut for SyslogSink {
     fn receive(&mut self, data: Vec<u8>) {
-        self.log_queue.push_back(data);
+        self.0.log_queue.write().unwrap().push_back(data);
     }
 }
 
 #[derive(Clone)]
 struct TrichechusServerImpl {
-    state: Rc<RefCell<TrichechusState>>,
+    state: Arc<TrichechusState>,
     transport_type: TransportType,
 }
 
 impl TrichechusServerImpl {
-    fn new(state: Rc<RefCell<TrichechusState>>, transport_type: TransportType) -> Self {
+    fn new(state: Arc<TrichechusState>, transport_type: TransportType) -> Self {
         TrichechusServerImpl {
             state,
             transport_type,
@@ -269,7 +818,7 @@ impl Trichechus for TrichechusServerImpl {
     fn start_session(&self, app_info: AppInfo) -> StdResult<(), ()> {
         info!("Received start session message: {:?}", &app_info);
         // The TEE app isn't started until its socket connection is accepted.
-        self.state.borrow_mut().pending_apps.insert(
+        self.state.pending_apps.write().unwrap().insert(
             self.port_to_transport_type(app_info.port_number),
             app_info.app_id,
         );
@@ -278,34 +827,473 @@ impl Trichechus for TrichechusServerImpl {
 
     fn get_logs(&self) -> StdResult<Vec<Vec<u8>>, ()> {
         let mut replacement: VecDeque<Vec<u8>> = VecDeque::new();
-        swap(&mut self.state.borrow_mut().log_queue, &mut replacement);
+        swap(
+            &mut *self.state.log_queue.write().unwrap(),
+            &mut replacement,
+        );
         Ok(replacement.into())
     }
+
+    fn list_apps(&self) -> StdResult<Vec<AppStatus>, ()> {
+        Ok(self.state.list_apps())
+    }
+
+    fn get_app_info(&self, app_id: String) -> StdResult<AppManifestEntry, ()> {
+        self.state
+            .app_manifest
+            .get_app_manifest_entry(&app_id)
+            .map(|entry| entry.to_owned())
+            .map_err(|err| error!("failed to look up app manifest entry '{}': {}", app_id, err))
+    }
+
+    fn stop_app(&self, transport_id: String) -> StdResult<(), ()> {
+        match self.state.stop_app(&transport_id) {
+            Some(pid) => {
+                info!(
+                    "sent SIGTERM to tee app (pid {}) for stop_app('{}')",
+                    pid, transport_id
+                );
+                Ok(())
+            }
+            None => {
+                error!(
+                    "stop_app: no running tee app with transport id '{}'",
+                    transport_id
+                );
+                Err(())
+            }
+        }
+    }
+}
+
+/// Identifies the peer a connection came from for rate-limiting purposes: the vsock CID for
+/// vsock connections, the peer IP (ignoring port, since a single peer may use many ports) for
+/// TCP ones.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+enum ConnectionSource {
+    Vsock(u32),
+    Ip(IpAddr),
+}
+
+fn connection_source(transport_type: &TransportType) -> Option<ConnectionSource> {
+    match transport_type {
+        TransportType::VsockConnection(addr) => Some(ConnectionSource::Vsock(addr.cid)),
+        TransportType::IpConnection(addr) => Some(ConnectionSource::Ip(addr.ip())),
+        _ => None,
+    }
+}
+
+/// A token bucket that refills at `rate` tokens/second up to `burst`, used to throttle a single
+/// connection source.
+struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    last_update: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, burst: f64) -> Self {
+        TokenBucket {
+            rate,
+            burst,
+            tokens: burst,
+            last_update: Instant::now(),
+        }
+    }
+
+    /// Refills based on elapsed time and consumes a token if one is available.
+    fn try_consume(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_update).as_secs_f64();
+        self.last_update = now;
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn idle_for(&self, now: Instant) -> Duration {
+        now.duration_since(self.last_update)
+    }
+}
+
+/// Per-source token-bucket rate limiter used by `ConnectionHandler::handle_incoming_connection`
+/// to resist connection floods/DOS attempts.
+struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    idle_timeout: Duration,
+    buckets: HashMap<ConnectionSource, TokenBucket>,
+}
+
+impl RateLimiter {
+    fn new(rate: f64, burst: f64, idle_timeout: Duration) -> Self {
+        RateLimiter {
+            rate,
+            burst,
+            idle_timeout,
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Returns whether `source` is allowed to proceed, consuming a token if so. Also evicts
+    /// buckets that have been idle past `idle_timeout` so a flood of one-off sources can't grow
+    /// this map without bound.
+    fn allow(&mut self, source: ConnectionSource) -> bool {
+        let now = Instant::now();
+        let idle_timeout = self.idle_timeout;
+        self.buckets
+            .retain(|_, bucket| bucket.idle_for(now) < idle_timeout);
+        let (rate, burst) = (self.rate, self.burst);
+        self.buckets
+            .entry(source)
+            .or_insert_with(|| TokenBucket::new(rate, burst))
+            .try_consume()
+    }
+}
+
+#[cfg(test)]
+mod rate_limit_tests {
+    use super::*;
+
+    #[test]
+    fn token_bucket_try_consume_respects_burst() {
+        let mut bucket = TokenBucket::new(1.0, 2.0);
+        assert!(bucket.try_consume());
+        assert!(bucket.try_consume());
+        assert!(!bucket.try_consume());
+    }
+
+    #[test]
+    fn rate_limiter_evicts_idle_buckets() {
+        let mut limiter = RateLimiter::new(1.0, 1.0, Duration::from_secs(60));
+        let stale_source = ConnectionSource::Vsock(42);
+        assert!(limiter.allow(stale_source.clone()));
+        assert_eq!(limiter.buckets.len(), 1);
+
+        limiter.buckets.get_mut(&stale_source).unwrap().last_update =
+            Instant::now() - Duration::from_secs(120);
+
+        // Calling `allow` for an unrelated source should evict the stale bucket above, not just
+        // add to it.
+        assert!(limiter.allow(ConnectionSource::Vsock(7)));
+        assert!(!limiter.buckets.contains_key(&stale_source));
+        assert_eq!(limiter.buckets.len(), 1);
+    }
+}
+
+/// A connection handed off from the acceptor to a worker thread, carrying just enough to build
+/// its `EventSource` on the worker itself so nothing crosses threads that isn't provably `Send`.
+enum ConnectionWork {
+    TeeApp {
+        app_id: String,
+        connection: Transport,
+    },
+    Control {
+        connection: Transport,
+    },
+    /// Removes fds this worker previously registered, e.g. because `ChildReaper` or
+    /// `DeadAppSweeper` discovered the owning app exited. Sent instead of removing the fds
+    /// directly because only the worker thread may mutate its own `EventMultiplexer`.
+    RemoveFds(Vec<RawFd>),
+    /// Tells the worker to tear down every `EventSource` on its ctx (including its own
+    /// `WorkQueue`) so its event loop exits, mirroring how the main ctx drains on
+    /// `TrichechusState::is_shutdown_requested()`.
+    Shutdown,
+}
+
+/// Adds a batch of newly constructed event sources to the `EventMultiplexer` they were built
+/// for; the counterpart to `RemoveFdsMutator`.
+struct AddEventSourcesMutator(Vec<Box<dyn EventSource>>);
+
+impl Mutator for AddEventSourcesMutator {
+    fn mutate(&mut self, event_multiplexer: &mut EventMultiplexer) {
+        for source in self.0.drain(..) {
+            if let Err(e) = event_multiplexer.add_event(source) {
+                error!("failed to add event source: {}", e);
+            }
+        }
+    }
+}
+
+/// Like `AddEventSourcesMutator` and `RemoveFdsMutator` combined, for the one place (`WorkQueue`)
+/// that can have both new sources to add and fds to remove out of the same drain of queued work:
+/// removes first, so a just-reused fd value can't collide with one still pending removal.
+struct WorkerMutators {
+    remove_fds: Vec<RawFd>,
+    add_sources: Vec<Box<dyn EventSource>>,
+}
+
+impl Mutator for WorkerMutators {
+    fn mutate(&mut self, event_multiplexer: &mut EventMultiplexer) {
+        for fd in self.remove_fds.drain(..) {
+            if let Err(e) = event_multiplexer.remove_event_for_fd(&fd) {
+                error!("failed to remove event source for fd {}: {}", fd, e);
+            }
+        }
+        for source in self.add_sources.drain(..) {
+            if let Err(e) = event_multiplexer.add_event(source) {
+                error!("failed to add event source: {}", e);
+            }
+        }
+    }
+}
+
+/// Wakes a worker thread's `EventMultiplexer` and drains whatever `ConnectionWork` the acceptor
+/// queued for it, building each connection's `EventSource` on the worker thread itself.
+struct WorkQueue {
+    wake_fd: EventFd,
+    receiver: mpsc::Receiver<ConnectionWork>,
+    state: Arc<TrichechusState>,
+    /// This worker's index into `Dispatch::Workers::workers`, stamped onto every `TeeApp` spawned
+    /// here (see `TeeApp::owner`) so `TrichechusState::evict_fds` can route removal back to us.
+    worker_index: usize,
+    /// Every fd this worker's ctx has registered via `new_sources` so far (not counting our own
+    /// `wake_fd`), so a `ConnectionWork::Shutdown` can empty the whole ctx and let the worker's
+    /// `while !ctx.is_empty()` loop exit on its own instead of waiting out the grace period.
+    owned_fds: Vec<RawFd>,
+}
+
+impl AsRawFd for WorkQueue {
+    fn as_raw_fd(&self) -> RawFd {
+        self.wake_fd.as_raw_fd()
+    }
+}
+
+impl EventSource for WorkQueue {
+    fn on_event(&mut self) -> StdResult<Option<Box<dyn Mutator>>, String> {
+        self.wake_fd.read().map_err(|e| e.to_string())?;
+        let mut new_sources: Vec<Box<dyn EventSource>> = Vec::new();
+        let mut remove_fds: Vec<RawFd> = Vec::new();
+        let mut shutdown = false;
+        while let Ok(work) = self.receiver.try_recv() {
+            match work {
+                ConnectionWork::TeeApp { app_id, connection } => {
+                    let id = connection.id.clone();
+                    match spawn_tee_app(
+                        &self.state.app_manifest,
+                        &app_id,
+                        connection,
+                        self.state.max_recording_bytes,
+                        Some(self.worker_index),
+                    ) {
+                        Ok((app, transport, recording_sources)) => {
+                            let tee_app = Arc::new(RwLock::new(app));
+                            self.state
+                                .running_apps
+                                .write()
+                                .unwrap()
+                                .insert(id, tee_app.clone());
+                            let storage_server: Box<dyn StorageRpcServer> =
+                                Box::new(TeeAppHandler {
+                                    state: self.state.clone(),
+                                    tee_app,
+                                });
+                            new_sources
+                                .push(Box::new(RpcDispatcher::new(storage_server, transport)));
+                            new_sources.extend(recording_sources);
+                        }
+                        Err(e) => error!("failed to start tee app: {}", e),
+                    }
+                }
+                ConnectionWork::Control { connection } => {
+                    let transport_type = connection.id.clone();
+                    new_sources.push(Box::new(RpcDispatcher::new(
+                        TrichechusServerImpl::new(self.state.clone(), transport_type).box_clone(),
+                        connection,
+                    )));
+                }
+                ConnectionWork::RemoveFds(fds) => {
+                    self.owned_fds.retain(|fd| !fds.contains(fd));
+                    remove_fds.extend(fds);
+                }
+                ConnectionWork::Shutdown => shutdown = true,
+            }
+        }
+        self.owned_fds
+            .extend(new_sources.iter().map(|s| s.as_raw_fd()));
+        if shutdown {
+            // Tear down every fd this ctx owns, including our own wake_fd: once the ctx is empty
+            // the worker's `while !ctx.is_empty()` loop exits on its own, the same way a clean
+            // shutdown already works on the main ctx via `is_shutdown_requested()`.
+            remove_fds.append(&mut self.owned_fds);
+            remove_fds.push(self.as_raw_fd());
+            return Ok(Some(Box::new(WorkerMutators {
+                remove_fds,
+                add_sources: Vec::new(),
+            })));
+        }
+        if new_sources.is_empty() && remove_fds.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(Box::new(WorkerMutators {
+                remove_fds,
+                add_sources: new_sources,
+            })))
+        }
+    }
+}
+
+/// Handle the acceptor thread keeps for a worker: queues it work and wakes its own
+/// `EventMultiplexer` (which blocks in `epoll`-style readiness, not the channel) to pick it up.
+struct WorkerHandle {
+    sender: mpsc::Sender<ConnectionWork>,
+    wake_fd: EventFd,
+}
+
+impl WorkerHandle {
+    fn dispatch(&self, work: ConnectionWork) {
+        if self.sender.send(work).is_err() {
+            error!("worker thread has exited, dropping connection");
+            return;
+        }
+        if let Err(e) = self.wake_fd.write(1) {
+            error!("failed to wake worker thread: {}", e);
+        }
+    }
+}
+
+/// Spawns a worker thread running its own `EventMultiplexer`, fed by a `WorkQueue`. `worker_index`
+/// is this worker's position in `Dispatch::Workers::workers`, threaded through to `WorkQueue` so
+/// it can be stamped onto every `TeeApp` it spawns.
+fn spawn_worker(state: Arc<TrichechusState>, worker_index: usize) -> Result<WorkerHandle> {
+    let (sender, receiver) = mpsc::channel();
+    let wake_fd = EventFd::new().map_err(Error::CreateEventFd)?;
+    let worker_wake_fd = wake_fd.try_clone().map_err(Error::CreateEventFd)?;
+    thread::Builder::new()
+        .name("trichechus_worker".to_string())
+        .spawn(move || {
+            let mut ctx = EventMultiplexer::new().unwrap();
+            ctx.add_event(Box::new(WorkQueue {
+                wake_fd: worker_wake_fd,
+                receiver,
+                state,
+                worker_index,
+                owned_fds: Vec::new(),
+            }))
+            .unwrap();
+            while !ctx.is_empty() {
+                if let Err(e) = ctx.run_once() {
+                    error!("worker event loop error: {}", e);
+                }
+            }
+        })
+        .expect("failed to spawn trichechus worker thread");
+    Ok(WorkerHandle { sender, wake_fd })
+}
+
+/// How incoming connections are turned into `EventSource`s: directly on the acceptor's own
+/// `EventMultiplexer` (the original single-threaded behavior, kept for constrained deployments),
+/// or handed off round-robin to a worker pool so one slow app can't stall the others.
+enum Dispatch {
+    Direct,
+    Workers {
+        workers: Vec<WorkerHandle>,
+        next: Cell<usize>,
+    },
+}
+
+impl Dispatch {
+    /// Routes removal of `fds` to the worker at `idx` (stamped as a `TeeApp::owner` when it was
+    /// spawned). `idx` is only ever produced by `Dispatch::Workers`, so this is a no-op under
+    /// `Dispatch::Direct` other than logging, which should never happen in practice.
+    fn remove_fds(&self, idx: usize, fds: Vec<RawFd>) {
+        match self {
+            Dispatch::Direct => {
+                error!("asked to route fd removal to worker {} under Dispatch::Direct", idx)
+            }
+            Dispatch::Workers { workers, .. } => match workers.get(idx) {
+                Some(worker) => worker.dispatch(ConnectionWork::RemoveFds(fds)),
+                None => error!("no worker at index {} to route fd removal to", idx),
+            },
+        }
+    }
+
+    /// Tells every worker (if any) to drain its ctx and exit, so a clean shutdown doesn't have to
+    /// rely on the grace-period force-kill of the remaining TEE apps.
+    fn shutdown_workers(&self) {
+        if let Dispatch::Workers { workers, .. } = self {
+            for worker in workers {
+                worker.dispatch(ConnectionWork::Shutdown);
+            }
+        }
+    }
 }
 
 struct DugongConnectionHandler {
-    state: Rc<RefCell<TrichechusState>>,
+    state: Arc<TrichechusState>,
+    control_rate_limiter: RateLimiter,
+    app_rate_limiter: RateLimiter,
 }
 
 impl DugongConnectionHandler {
-    fn new(state: Rc<RefCell<TrichechusState>>) -> Self {
-        DugongConnectionHandler { state }
+    fn new(
+        state: Arc<TrichechusState>,
+        control_rate_limiter: RateLimiter,
+        app_rate_limiter: RateLimiter,
+    ) -> Self {
+        DugongConnectionHandler {
+            state,
+            control_rate_limiter,
+            app_rate_limiter,
+        }
+    }
+
+    /// Hands `work` to the next worker in round-robin order, or returns it if running
+    /// `Dispatch::Direct`, so the caller can build the `EventSource` on the acceptor itself.
+    fn dispatch_or_return(&self, work: ConnectionWork) -> Option<ConnectionWork> {
+        match self.state.dispatch.read().unwrap().as_ref() {
+            None | Some(Dispatch::Direct) => Some(work),
+            Some(Dispatch::Workers { workers, next }) => {
+                let idx = next.get();
+                next.set((idx + 1) % workers.len());
+                workers[idx].dispatch(work);
+                None
+            }
+        }
     }
 
     fn connect_tee_app(&mut self, app_id: &str, connection: Transport) -> Option<Box<dyn Mutator>> {
+        let work = self.dispatch_or_return(ConnectionWork::TeeApp {
+            app_id: app_id.to_string(),
+            connection,
+        })?;
+        let (app_id, connection) = match work {
+            ConnectionWork::TeeApp { app_id, connection } => (app_id, connection),
+            ConnectionWork::Control { .. }
+            | ConnectionWork::RemoveFds(_)
+            | ConnectionWork::Shutdown => unreachable!(),
+        };
         let id = connection.id.clone();
-        let state = self.state.clone();
-        // Only borrow once.
-        let mut trichechus_state = self.state.borrow_mut();
-        match spawn_tee_app(&trichechus_state.app_manifest, app_id, connection) {
-            Ok((app, transport)) => {
-                let tee_app = Rc::new(RefCell::new(app));
-                trichechus_state.running_apps.insert(id, tee_app.clone());
-                let storage_server: Box<dyn StorageRpcServer> =
-                    Box::new(TeeAppHandler { state, tee_app });
-                Some(Box::new(AddEventSourceMutator(Some(Box::new(
-                    RpcDispatcher::new(storage_server, transport),
-                )))))
+        // `dispatch_or_return` only hands this back to us when running `Dispatch::Direct` (or
+        // before `set_dispatch` has installed anything), so this app's sources belong on the
+        // acceptor's own ctx: `owner: None`.
+        match spawn_tee_app(
+            &self.state.app_manifest,
+            &app_id,
+            connection,
+            self.state.max_recording_bytes,
+            None,
+        ) {
+            Ok((app, transport, recording_sources)) => {
+                let tee_app = Arc::new(RwLock::new(app));
+                self.state
+                    .running_apps
+                    .write()
+                    .unwrap()
+                    .insert(id, tee_app.clone());
+                let storage_server: Box<dyn StorageRpcServer> = Box::new(TeeAppHandler {
+                    state: self.state.clone(),
+                    tee_app,
+                });
+                let mut sources: Vec<Box<dyn EventSource>> =
+                    vec![Box::new(RpcDispatcher::new(storage_server, transport))];
+                sources.extend(recording_sources);
+                Some(Box::new(AddEventSourcesMutator(sources)))
             }
             Err(e) => {
                 error!("failed to start tee app: {}", e);
@@ -313,15 +1301,57 @@ impl DugongConnectionHandler {
             }
         }
     }
+
+    fn connect_control(&mut self, connection: Transport) -> Option<Box<dyn Mutator>> {
+        let work = self.dispatch_or_return(ConnectionWork::Control { connection })?;
+        let connection = match work {
+            ConnectionWork::Control { connection } => connection,
+            ConnectionWork::TeeApp { .. }
+            | ConnectionWork::RemoveFds(_)
+            | ConnectionWork::Shutdown => unreachable!(),
+        };
+        let transport_type = connection.id.clone();
+        Some(Box::new(AddEventSourceMutator(Some(Box::new(
+            RpcDispatcher::new(
+                TrichechusServerImpl::new(self.state.clone(), transport_type).box_clone(),
+                connection,
+            ),
+        )))))
+    }
 }
 
 impl ConnectionHandler for DugongConnectionHandler {
     fn handle_incoming_connection(&mut self, connection: Transport) -> Option<Box<dyn Mutator>> {
         info!("incoming connection '{:?}'", &connection.id);
-        let expected_port = self.state.borrow().expected_port;
+        let is_tee_app_conn = self
+            .state
+            .pending_apps
+            .read()
+            .unwrap()
+            .contains_key(&connection.id);
+        if let Some(source) = connection_source(&connection.id) {
+            let limiter = if is_tee_app_conn {
+                &mut self.app_rate_limiter
+            } else {
+                &mut self.control_rate_limiter
+            };
+            if !limiter.allow(source.clone()) {
+                error!(
+                    "throttling connection from {:?}: rate limit exceeded",
+                    source
+                );
+                return None;
+            }
+        }
+        let expected_port = *self.state.expected_port.read().unwrap();
         // Check if the incoming connection is expected and associated with a TEE
         // application.
-        let reservation = self.state.borrow_mut().pending_apps.remove(&connection.id);
+        let reservation = self
+            .state
+            .pending_apps
+            .write()
+            .unwrap()
+            .remove(&connection.id);
         if let Some(app_id) = reservation {
             info!("starting instance of '{}'", app_id);
             self.connect_tee_app(&app_id, connection)
@@ -330,13 +1360,7 @@ impl ConnectionHandler for DugongConnectionHandler {
             match connection.id.get_port() {
                 Ok(port) if port == expected_port => {
                     info!("new control connection.");
-                    Some(Box::new(AddEventSourceMutator(Some(Box::new(
-                        RpcDispatcher::new(
-                            TrichechusServerImpl::new(self.state.clone(), connection.id.clone())
-                                .box_clone(),
-                            connection,
-                        ),
-                    )))))
+                    self.connect_control(connection)
                 }
                 _ => {
                     error!("dropping unexpected connection.");
@@ -347,11 +1371,266 @@ impl ConnectionHandler for DugongConnectionHandler {
     }
 }
 
+/// Reacts to SIGTERM/SIGINT by flushing buffered logs, tearing down every running TEE app, and
+/// closing the persistence connection, then asking the event loop to exit instead of letting the
+/// process be killed out from under its children.
+struct ShutdownHandler {
+    signal_fd: SignalFd,
+    state: Arc<TrichechusState>,
+    grace_period: Duration,
+}
+
+impl ShutdownHandler {
+    fn new(
+        signal: libc::c_int,
+        state: Arc<TrichechusState>,
+        grace_period: Duration,
+    ) -> Result<Self> {
+        Ok(ShutdownHandler {
+            signal_fd: SignalFd::new(signal).map_err(Error::CreateSignalFd)?,
+            state,
+            grace_period,
+        })
+    }
+
+    fn shut_down(&self) {
+        info!("received shutdown signal, tearing down TEE apps");
+
+        self.state.flush_log_queue();
+
+        // Drain running_apps and SIGTERM each app so it gets a chance to exit on its own. The
+        // pids are captured right here, under the same lock that removes their entries, so the
+        // grace-period force-kill below always targets the apps that were actually running at
+        // shutdown time rather than re-resolving them later by a pid the kernel may have since
+        // recycled for an unrelated process.
+        let mut pids: Vec<pid_t> = self
+            .state
+            .running_apps
+            .write()
+            .unwrap()
+            .drain()
+            .map(|(_, tee_app)| {
+                let tee_app = tee_app.read().unwrap();
+                info!(
+                    "sending SIGTERM to tee app '{}' (pid {}) for shutdown",
+                    tee_app.app_info.app_name, tee_app.pid
+                );
+                unsafe { libc::kill(tee_app.pid, libc::SIGTERM) };
+                tee_app.pid
+            })
+            .collect();
+
+        self.state.drop_persistence();
+        self.state.request_shutdown();
+        self.state.shutdown_workers();
+
+        // If any of the above ignore SIGTERM, force-kill them directly once the grace period
+        // elapses. This targets each app's own pid rather than its process group: trichechus only
+        // calls setsid() in the original (pre-fork) process, so the event-loop process handling
+        // shutdown here is not a process group leader, and a group-wide SIGKILL would both miss
+        // the actual target (ESRCH) and, if it somehow matched, take the parent reaper down too.
+        // Waited out here rather than on a detached thread, which would end with the process as
+        // soon as `main` sees `is_shutdown_requested()` and returns.
+        let deadline = Instant::now() + self.grace_period;
+        while !pids.is_empty() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(100));
+            pids.retain(|&pid| !process_has_exited(pid));
+        }
+        if !pids.is_empty() {
+            error!("shutdown grace period elapsed, force-killing remaining TEE apps");
+            for pid in pids {
+                unsafe { libc::kill(pid, libc::SIGKILL) };
+            }
+        }
+    }
+}
+
+impl AsRawFd for ShutdownHandler {
+    fn as_raw_fd(&self) -> RawFd {
+        self.signal_fd.as_raw_fd()
+    }
+}
+
+impl EventSource for ShutdownHandler {
+    fn on_event(&mut self) -> StdResult<Option<Box<dyn Mutator>>, String> {
+        self.signal_fd
+            .read()
+            .map_err(Error::ReadSignalFd)
+            .map_err(|e| e.to_string())?;
+        self.shut_down();
+        Ok(None)
+    }
+}
+
+/// Evicts a batch of fds from the `EventMultiplexer`, e.g. the `RpcDispatcher`s of TEE apps that
+/// have exited.
+struct RemoveFdsMutator(Vec<RawFd>);
+
+impl Mutator for RemoveFdsMutator {
+    fn mutate(&mut self, event_multiplexer: &mut EventMultiplexer) {
+        for fd in self.0.drain(..) {
+            if let Err(e) = event_multiplexer.remove_event_for_fd(&fd) {
+                error!("failed to remove event source for fd {}: {}", fd, e);
+            }
+        }
+    }
+}
+
+/// Reaps exited TEE apps and evicts them from `TrichechusState::running_apps` (and the
+/// `EventMultiplexer`) on SIGCHLD, so finished TEEs aren't leaked.
+struct ChildReaper {
+    signal_fd: SignalFd,
+    state: Arc<TrichechusState>,
+}
+
+impl ChildReaper {
+    fn new(state: Arc<TrichechusState>) -> Result<Self> {
+        Ok(ChildReaper {
+            signal_fd: SignalFd::new(libc::SIGCHLD).map_err(Error::CreateSignalFd)?,
+            state,
+        })
+    }
+}
+
+impl AsRawFd for ChildReaper {
+    fn as_raw_fd(&self) -> RawFd {
+        self.signal_fd.as_raw_fd()
+    }
+}
+
+impl EventSource for ChildReaper {
+    fn on_event(&mut self) -> StdResult<Option<Box<dyn Mutator>>, String> {
+        self.signal_fd
+            .read()
+            .map_err(Error::ReadSignalFd)
+            .map_err(|e| e.to_string())?;
+
+        // Drain every child that has already exited without blocking for more.
+        let mut dispatcher_fds = Vec::new();
+        while let Some((_, fds)) = self.state.reap_and_evict_one() {
+            dispatcher_fds.extend(fds);
+        }
+        Ok(Some(Box::new(RemoveFdsMutator(dispatcher_fds))))
+    }
+}
+
+/// Periodically sweeps `TrichechusState::running_apps` for TEE apps that exited without being
+/// reaped, as a backstop for when a SIGCHLD is missed (e.g. blocked or lost).
+struct DeadAppSweeper {
+    timer_fd: TimerFd,
+    state: Arc<TrichechusState>,
+}
+
+impl DeadAppSweeper {
+    fn new(state: Arc<TrichechusState>, interval: Duration) -> Result<Self> {
+        let mut timer_fd = TimerFd::new().map_err(Error::CreateTimerFd)?;
+        timer_fd
+            .reset(interval, Some(interval))
+            .map_err(Error::ArmTimerFd)?;
+        Ok(DeadAppSweeper { timer_fd, state })
+    }
+}
+
+impl AsRawFd for DeadAppSweeper {
+    fn as_raw_fd(&self) -> RawFd {
+        self.timer_fd.as_raw_fd()
+    }
+}
+
+impl EventSource for DeadAppSweeper {
+    fn on_event(&mut self) -> StdResult<Option<Box<dyn Mutator>>, String> {
+        self.timer_fd.wait().map_err(|e| e.to_string())?;
+        let dispatcher_fds = self.state.sweep_dead_apps();
+        Ok(Some(Box::new(RemoveFdsMutator(dispatcher_fds))))
+    }
+}
+
+/// Loads the `AppManifest` used to register TEE applications.
+///
+/// When `path` is given, the manifest is deserialized from the YAML or TOML file at that
+/// location (selected by file extension, defaulting to YAML) and validated. When `path` is
+/// `None`, the hardcoded default manifest is used instead so trichechus keeps working on
+/// deployments that don't supply an external manifest.
+fn load_app_manifest(path: Option<&Path>) -> Result<AppManifest> {
+    let path = match path {
+        Some(path) => path,
+        None => return Ok(AppManifest::new()),
+    };
+    let contents =
+        fs::read_to_string(path).map_err(|e| Error::ReadAppManifest(path.to_owned(), e))?;
+    let app_manifest: AppManifest = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::from_str(&contents)
+            .map_err(|e| Error::ParseAppManifest(path.to_owned(), e.to_string()))?,
+        _ => serde_yaml::from_str(&contents)
+            .map_err(|e| Error::ParseAppManifest(path.to_owned(), e.to_string()))?,
+    };
+    validate_app_manifest(&app_manifest)?;
+    Ok(app_manifest)
+}
+
+/// Rejects manifest entries whose `StorageParameters` request an encryption key version that
+/// `SecretManager` has no way of deriving, so a bad manifest is caught before it is used to
+/// build the `SecretManager`.
+fn validate_app_manifest(app_manifest: &AppManifest) -> Result<()> {
+    for entry in app_manifest.iter() {
+        if let Some(params) = &entry.storage_parameters {
+            if let Some(version) = params.encryption_key_version {
+                if version > secrets::MAX_VERSION {
+                    return Err(Error::UnsupportedEncryptionKeyVersion(
+                        entry.app_name.to_owned(),
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod validate_app_manifest_tests {
+    use super::*;
+
+    // Mirrors the on-disk manifest shape `load_app_manifest` parses with `serde_yaml`.
+    const MANIFEST_YAML: &str = r#"
+apps:
+  - app_name: "test_app"
+    path: "/usr/bin/test_app"
+    sandbox_type: DeveloperEnvironment
+    record_session: false
+    storage_parameters:
+      scope: Test
+      domain: "test"
+      encryption_key_version: 999
+"#;
+
+    #[test]
+    fn validate_app_manifest_rejects_unsupported_encryption_key_version() {
+        let app_manifest: AppManifest =
+            serde_yaml::from_str(MANIFEST_YAML).expect("failed to parse test manifest");
+        assert!(matches!(
+            validate_app_manifest(&app_manifest),
+            Err(Error::UnsupportedEncryptionKeyVersion(name)) if name == "test_app"
+        ));
+    }
+
+    #[test]
+    fn validate_app_manifest_accepts_empty_manifest() {
+        assert!(validate_app_manifest(&AppManifest::new()).is_ok());
+    }
+}
+
+/// Starts the sandboxed process for `app_id` and wires up its transport, stderr, and (if
+/// `app_info.record_session` is set) a session recording bounded to `max_recording_bytes`.
+/// Returns the `TeeApp`, its dispatcher transport, and any `RecordingReader`s the caller must
+/// register on whichever `EventMultiplexer` will own this app's other `EventSource`s. `owner`
+/// records which ctx that will be (see `TeeApp::owner`) so eviction can later be routed there.
 fn spawn_tee_app(
     app_manifest: &AppManifest,
     app_id: &str,
     transport: Transport,
-) -> Result<(TeeApp, Transport)> {
+    max_recording_bytes: usize,
+    owner: Option<usize>,
+) -> Result<(TeeApp, Transport, Vec<Box<dyn EventSource>>)> {
     let app_info = app_manifest
         .get_app_manifest_entry(app_id)
         .map_err(Error::AppManifest)?;
@@ -364,30 +1643,110 @@ fn spawn_tee_app(
     };
     let (trichechus_transport, tee_transport) =
         create_transport_from_pipes().map_err(Error::NewTransport)?;
-    let keep_fds: [(RawFd, RawFd); 5] = [
+    let mut keep_fds: Vec<(RawFd, RawFd)> = vec![
         (transport.r.as_raw_fd(), CROS_CONNECTION_R_FD),
         (transport.w.as_raw_fd(), CROS_CONNECTION_W_FD),
-        (stderr().as_raw_fd(), CROS_CONNECTION_ERR_FD),
         (tee_transport.r.as_raw_fd(), DEFAULT_CONNECTION_R_FD),
         (tee_transport.w.as_raw_fd(), DEFAULT_CONNECTION_W_FD),
     ];
+
+    let dispatcher_fd = trichechus_transport.r.as_raw_fd();
+    let mut initial_live_fds = HashSet::new();
+    initial_live_fds.insert(dispatcher_fd);
+    let live_fds = Arc::new(Mutex::new(initial_live_fds));
+
+    let mut recording = None;
+    let mut recording_fds = Vec::new();
+    let mut recording_sources: Vec<Box<dyn EventSource>> = Vec::new();
+    // Our copies of the pipe write ends, kept alive only until sandbox.run() has dup2'd them
+    // into the child, then dropped so trichechus itself doesn't hold them open (which would
+    // stop the corresponding `RecordingReader` from ever seeing EOF once the app exits).
+    let mut recording_pipe_writers: Vec<fs::File> = Vec::new();
+
+    if app_info.record_session {
+        let session_recording = Arc::new(Mutex::new(SessionRecording::new(max_recording_bytes)));
+        for (stream, target_fd) in [
+            (RecordingStream::Stderr, CROS_CONNECTION_ERR_FD),
+            (RecordingStream::Stdout, libc::STDOUT_FILENO),
+        ] {
+            let (read_end, write_fd) = open_recording_pipe()?;
+            keep_fds.push((write_fd, target_fd));
+            recording_fds.push(read_end.as_raw_fd());
+            live_fds.lock().unwrap().insert(read_end.as_raw_fd());
+            recording_sources.push(Box::new(RecordingReader {
+                stream,
+                pipe: read_end,
+                recording: session_recording.clone(),
+                live_fds: live_fds.clone(),
+            }));
+            recording_pipe_writers.push(unsafe { fs::File::from_raw_fd(write_fd) });
+        }
+        recording = Some(session_recording);
+    } else {
+        keep_fds.push((stderr().as_raw_fd(), CROS_CONNECTION_ERR_FD));
+    }
+
     let process_path = app_info.path.to_string();
 
     sandbox
         .run(Path::new(&process_path), &[&process_path], &keep_fds)
         .map_err(Error::RunSandbox)?;
+    drop(recording_pipe_writers);
+
+    let pid = sandbox.pid();
 
     Ok((
         TeeApp {
             _sandbox: sandbox,
             app_info: app_info.to_owned(),
+            pid,
+            dispatcher_fd,
+            recording,
+            recording_fds,
+            live_fds,
+            started: Instant::now(),
+            owner,
         },
         trichechus_transport,
+        recording_sources,
     ))
 }
 
-// TODO: Figure out how to clean up TEEs that are no longer in use
-// TODO: Figure out rate limiting and prevention against DOS attacks
+/// Implements the `--status`/`--stop` one-shot CLI modes: opens a control connection to an
+/// already-running trichechus, invokes the requested management RPC, prints the result, and
+/// returns so `main` can exit without starting the event loop, mirroring the existing
+/// `--syslog-path` one-shot behavior.
+fn run_management_command(
+    connection_type: &TransportType,
+    matches: &getopts::Matches,
+) -> Result<()> {
+    let transport = connection_type
+        .try_into_client(None)
+        .map_err(Error::NewTransport)?
+        .connect()
+        .map_err(Error::NewTransport)?;
+    let client = TrichechusClient::new(transport);
+    if let Some(transport_id) = matches.opt_str(STOP_LONG_NAME) {
+        client
+            .stop_app(transport_id.clone())
+            .map_err(Error::ManagementRpc)?;
+        println!("stopped tee app with transport id '{}'", transport_id);
+    } else {
+        let apps = client.list_apps().map_err(Error::ManagementRpc)?;
+        if apps.is_empty() {
+            println!("no TEE apps are currently running");
+        } else {
+            for app in apps {
+                println!(
+                    "{}\ttransport={}\tsandbox={:?}\tuptime={}s",
+                    app.app_id, app.transport_id, app.sandbox_type, app.uptime_secs
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
 // TODO: What happens if dugong crashes? How do we want to handle
 fn main() -> Result<()> {
     // Handle the arguments first since "-h" shouldn't have any side effects on the system such as
@@ -400,6 +1759,74 @@ fn main() -> Result<()> {
         "connect to trichechus, get and print logs, then exit.",
         SYSLOG_PATH,
     );
+    opts.optopt(
+        APP_MANIFEST_SHORT_NAME,
+        APP_MANIFEST_LONG_NAME,
+        "path to a YAML or TOML file describing the TEE app manifest. Falls back to the \
+         built-in manifest when omitted.",
+        "FILE",
+    );
+    opts.optopt(
+        SHUTDOWN_GRACE_PERIOD_SHORT_NAME,
+        SHUTDOWN_GRACE_PERIOD_LONG_NAME,
+        "seconds to wait for TEE apps to tear down on SIGTERM/SIGINT before force-killing them",
+        &DEFAULT_SHUTDOWN_GRACE_PERIOD_SECS.to_string(),
+    );
+    opts.optopt(
+        "",
+        CONTROL_CONN_RATE_LONG_NAME,
+        "control connection accept rate, in connections/second/source",
+        &DEFAULT_CONTROL_CONN_RATE.to_string(),
+    );
+    opts.optopt(
+        "",
+        CONTROL_CONN_BURST_LONG_NAME,
+        "control connection accept burst size, per source",
+        &DEFAULT_CONTROL_CONN_BURST.to_string(),
+    );
+    opts.optopt(
+        "",
+        APP_CONN_RATE_LONG_NAME,
+        "tee app connection accept rate, in connections/second/source",
+        &DEFAULT_APP_CONN_RATE.to_string(),
+    );
+    opts.optopt(
+        "",
+        APP_CONN_BURST_LONG_NAME,
+        "tee app connection accept burst size, per source",
+        &DEFAULT_APP_CONN_BURST.to_string(),
+    );
+    opts.optflag(
+        "",
+        SINGLE_THREADED_LONG_NAME,
+        "dispatch every connection on the main thread's event loop instead of a worker pool, \
+         for constrained deployments",
+    );
+    opts.optopt(
+        "",
+        WORKER_THREADS_LONG_NAME,
+        "number of worker threads to dispatch connections to when not --single-threaded",
+        &DEFAULT_WORKER_THREADS.to_string(),
+    );
+    opts.optopt(
+        "",
+        SESSION_RECORDING_RING_BYTES_LONG_NAME,
+        "bytes of stdio to retain per TEE app session recording (see the manifest's \
+         record_session flag)",
+        &DEFAULT_SESSION_RECORDING_RING_BYTES.to_string(),
+    );
+    opts.optflag(
+        "",
+        STATUS_LONG_NAME,
+        "connect to a running trichechus, list its running TEE apps, then exit",
+    );
+    opts.optopt(
+        "",
+        STOP_LONG_NAME,
+        "connect to a running trichechus, stop the TEE app with the given transport id, then \
+         exit",
+        "TRANSPORT_ID",
+    );
     let cronista_uri_option = TransportTypeOption::new(
         CRONISTA_URI_SHORT_NAME,
         CRONISTA_URI_LONG_NAME,
@@ -408,6 +1835,37 @@ fn main() -> Result<()> {
         &mut opts,
     );
     let (config, matches) = initialize_common_arguments(opts, &args[1..]).unwrap();
+    if matches.opt_present(STATUS_LONG_NAME) || matches.opt_present(STOP_LONG_NAME) {
+        return run_management_command(&config.connection_type, &matches);
+    }
+    let app_manifest = load_app_manifest(
+        matches
+            .opt_str(APP_MANIFEST_SHORT_NAME)
+            .map(PathBuf::from)
+            .as_deref(),
+    )?;
+    let shutdown_grace_period = Duration::from_secs(
+        matches
+            .opt_str(SHUTDOWN_GRACE_PERIOD_SHORT_NAME)
+            .map(|secs| secs.parse().unwrap())
+            .unwrap_or(DEFAULT_SHUTDOWN_GRACE_PERIOD_SECS),
+    );
+    let opt_f64 = |name: &str, default: f64| -> f64 {
+        matches
+            .opt_str(name)
+            .map(|val| val.parse().unwrap())
+            .unwrap_or(default)
+    };
+    let control_rate_limiter = RateLimiter::new(
+        opt_f64(CONTROL_CONN_RATE_LONG_NAME, DEFAULT_CONTROL_CONN_RATE),
+        opt_f64(CONTROL_CONN_BURST_LONG_NAME, DEFAULT_CONTROL_CONN_BURST),
+        RATE_LIMITER_IDLE_TIMEOUT,
+    );
+    let app_rate_limiter = RateLimiter::new(
+        opt_f64(APP_CONN_RATE_LONG_NAME, DEFAULT_APP_CONN_RATE),
+        opt_f64(APP_CONN_BURST_LONG_NAME, DEFAULT_APP_CONN_BURST),
+        RATE_LIMITER_IDLE_TIMEOUT,
+    );
     // TODO derive main secret from the platform and GSC.
     let main_secret_version = 0usize;
     let platform_secret = PlatformSecret::new(
@@ -424,10 +1882,16 @@ fn main() -> Result<()> {
     )
     .derive_other_version(main_secret_version)
     .unwrap();
-    let state = Rc::new(RefCell::new(TrichechusState::new(
+    let max_recording_bytes = matches
+        .opt_str(SESSION_RECORDING_RING_BYTES_LONG_NAME)
+        .map(|v| v.parse().unwrap())
+        .unwrap_or(DEFAULT_SESSION_RECORDING_RING_BYTES);
+    let state = Arc::new(TrichechusState::new(
         platform_secret,
         gsc_secret,
-    )));
+        app_manifest,
+        max_recording_bytes,
+    ));
 
     // Create /dev/log if it doesn't already exist since trichechus is the first thing to run after
     // the kernel on the hypervisor.
@@ -438,7 +1902,7 @@ fn main() -> Result<()> {
     );
     let syslog: Option<Syslog> = if !log_path.exists() {
         eprintln!("Creating syslog.");
-        Some(Syslog::new(log_path, state.clone()).unwrap())
+        Some(Syslog::new(log_path, Rc::new(RefCell::new(SyslogSink(state.clone())))).unwrap())
     } else {
         eprintln!("Syslog exists.");
         None
@@ -474,21 +1938,76 @@ fn main() -> Result<()> {
     to_sys_util::unblock_all_signals();
 
     if let Some(uri) = cronista_uri_option.from_matches(&matches).unwrap() {
-        let mut state_mut = state.borrow_mut();
-        state_mut.persistence_uri = uri.clone();
-        *state_mut.persistence.borrow_mut().deref_mut() = Some(CronistaClient::new(
+        *state.persistence_uri.write().unwrap() = uri.clone();
+        *state.persistence.write().unwrap() = Some(CronistaClient::new(
             uri.try_into_client(None).unwrap().connect().unwrap(),
         ));
     }
 
+    // signalfd delivers a signal through its fd only while that signal stays blocked via
+    // sigprocmask, so re-block SIGTERM/SIGINT/SIGCHLD after the general unblock_all_signals()
+    // above.
+    unsafe {
+        let mut mask: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut mask);
+        libc::sigaddset(&mut mask, libc::SIGTERM);
+        libc::sigaddset(&mut mask, libc::SIGINT);
+        libc::sigaddset(&mut mask, libc::SIGCHLD);
+        libc::sigprocmask(libc::SIG_BLOCK, &mask, std::ptr::null_mut());
+    }
+
     let mut ctx = EventMultiplexer::new().unwrap();
     if let Some(event_source) = syslog {
         ctx.add_event(Box::new(event_source)).unwrap();
     }
+    for signal in [libc::SIGTERM, libc::SIGINT] {
+        match ShutdownHandler::new(signal, state.clone(), shutdown_grace_period) {
+            Ok(handler) => ctx.add_event(Box::new(handler)).unwrap(),
+            Err(e) => error!(
+                "failed to register shutdown handler for signal {}: {}",
+                signal, e
+            ),
+        }
+    }
+    match ChildReaper::new(state.clone()) {
+        Ok(reaper) => ctx.add_event(Box::new(reaper)).unwrap(),
+        Err(e) => error!("failed to register tee app reaper: {}", e),
+    }
+    match DeadAppSweeper::new(state.clone(), DEAD_APP_SWEEP_INTERVAL) {
+        Ok(sweeper) => ctx.add_event(Box::new(sweeper)).unwrap(),
+        Err(e) => error!("failed to register dead tee app sweeper: {}", e),
+    }
+
+    let dispatch = if matches.opt_present(SINGLE_THREADED_LONG_NAME) {
+        Dispatch::Direct
+    } else {
+        let worker_threads = matches
+            .opt_str(WORKER_THREADS_LONG_NAME)
+            .map(|v| v.parse().unwrap())
+            .unwrap_or(DEFAULT_WORKER_THREADS)
+            .max(1);
+        let mut workers = Vec::with_capacity(worker_threads);
+        for worker_index in 0..worker_threads {
+            match spawn_worker(state.clone(), worker_index) {
+                Ok(worker) => workers.push(worker),
+                Err(e) => error!("failed to spawn worker thread: {}", e),
+            }
+        }
+        if workers.is_empty() {
+            error!("failed to spawn any worker threads, falling back to single-threaded dispatch");
+            Dispatch::Direct
+        } else {
+            Dispatch::Workers {
+                workers,
+                next: Cell::new(0),
+            }
+        }
+    };
+    state.set_dispatch(dispatch);
 
     let server = TransportServer::new(
         &config.connection_type,
-        DugongConnectionHandler::new(state.clone()),
+        DugongConnectionHandler::new(state.clone(), control_rate_limiter, app_rate_limiter),
     )
     .unwrap();
     let listen_addr = server.bound_to();
@@ -500,18 +2019,19 @@ fn main() -> Result<()> {
         match addr.get_port() {
             Ok(DEFAULT_SERVER_PORT) | Err(_) => {}
             Ok(port) => {
-                state.borrow_mut().expected_port = port + 1;
+                *state.expected_port.write().unwrap() = port + 1;
             }
         }
         info!("waiting for connection at: {}", addr);
     } else {
         info!("waiting for connection");
     }
-    while !ctx.is_empty() {
+    while !ctx.is_empty() && !state.is_shutdown_requested() {
         if let Err(e) = ctx.run_once() {
             error!("{}", e);
         };
     }
+    info!("exiting trichechus event loop");
 
     Ok(())
 }